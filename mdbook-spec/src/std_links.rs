@@ -1,37 +1,65 @@
 use mdbook::book::Chapter;
 use once_cell::sync::Lazy;
-use regex::{Captures, Regex};
-use std::collections::HashSet;
+use pulldown_cmark::{BrokenLink, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write as _;
 use std::fs;
 use std::io::{self, Write as _};
+use std::ops::Range;
+use std::path::PathBuf;
 use std::process::{self, Command};
 use tempfile::TempDir;
 
-/// A markdown link (without the brackets) that might possibly be a link to
-/// the standard library using rustdoc's intra-doc notation.
-const STD_LINK: &str = r"(?: [a-z]+@ )?
-                         (?: std|core|alloc|proc_macro|test )
-                         (?: ::[A-Za-z0-9_!:<>{}()\[\]]+ )?";
-
-/// The Regex for a markdown link that might be a link to the standard library.
-static STD_LINK_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(&format!(
-        r"(?x)
-            (?:
-                ( \[`[^`]+`\] ) \( ({STD_LINK}) \)
-            )
-            | (?:
-                ( \[`{STD_LINK}`\] )
-            )
-         "
-    ))
+/// A regex that captures the root crate name out of a path (the
+/// destination of a link, or the text of a `` [`code`] `` style link),
+/// allowing for an optional rustdoc disambiguator prefix (`fn@`, `macro@`,
+/// etc.) and an optional `::...` item path after the crate name, which may
+/// itself end in generic arguments (`Vec<T>`, `Result<T, E>`) directly
+/// after the root with no `::` in between.
+static LINK_PATH_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x) ^
+          (?: [a-z]+@ )?
+          (?<root> [A-Za-z0-9_]+ )
+          (?: (?: :: )? [A-Za-z0-9_!:<>{}()\[\],\ ]+ )?
+          $",
+    )
     .unwrap()
 });
 
-/// The Regex used to extract the std links from the HTML generated by rustdoc.
-static STD_LINK_EXTRACT_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"<li><a [^>]*href="(https://doc.rust-lang.org/[^"]+)""#).unwrap());
+/// A regex that extracts the path out of the leading `` [`code`] `` portion
+/// of a link's source text, ignoring whatever (if anything) follows it
+/// (such as a `(dest)` or `[label]`).
+static CODE_LABEL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[`([^`]+)`\]").unwrap());
+
+/// The Regex used to extract the generated links from the HTML produced by
+/// rustdoc, whether they point to the standard library or to a
+/// third-party crate hosted on docs.rs.
+static LINK_EXTRACT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<li><a [^>]*href="(https://(?:doc\.rust-lang\.org|docs\.rs)/[^"]+)""#).unwrap()
+});
+
+/// Configuration for a third-party crate that intra-doc links may point
+/// to, in addition to the standard library. Configured in `book.toml`
+/// under `[preprocessor.spec] extern-crates`, keyed by crate name.
+#[derive(Debug, Clone, Default)]
+pub struct ExternCrate {
+    /// The version of the crate to link to on docs.rs, e.g. `"0.2.150"`.
+    pub version: String,
+    /// Path to a compiled rlib for this crate, built ahead of time (for
+    /// example via a small throwaway crate that depends on it), so
+    /// rustdoc can actually resolve items from it. Without this, there's
+    /// nothing for rustdoc to resolve the item against, so links into
+    /// this crate are silently excluded from [`collect_markdown_links`]
+    /// rather than sent to rustdoc at all; [`std_links_for_book`] warns
+    /// about crates configured this way.
+    pub rlib_path: Option<PathBuf>,
+}
+
+/// Crate name to configuration, read from `[preprocessor.spec] extern-crates`.
+pub type ExternCrates = BTreeMap<String, ExternCrate>;
 
 /// The Regex for a markdown link definition.
 static LINK_DEF_RE: Lazy<Regex> = Lazy::new(|| {
@@ -42,101 +70,329 @@ static LINK_DEF_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"(?m)^(?<label>\[[^]]+\]): *(?<dest>.*)"#).unwrap()
 });
 
-/// Converts links to the standard library to the online documentation in a
-/// fashion similar to rustdoc intra-doc links.
-pub fn std_links(chapter: &Chapter) -> String {
-    let links = collect_markdown_links(chapter);
-    if links.is_empty() {
-        return chapter.content.clone();
+/// A markdown link to the standard library (or to a configured third-party
+/// crate) found in a chapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MdLink<'a> {
+    /// The exact span of the link in `chapter.content`, used so the
+    /// rewrite step can edit it in place instead of a global find/replace.
+    range: Range<usize>,
+    /// The source text of the link itself, not including an explicit
+    /// destination, such as `` [`Option`] ``.
+    text: &'a str,
+    /// The destination, if explicitly given, such as in
+    /// ``[`Option`](std::option::Option)``.
+    dest: Option<&'a str>,
+    /// The path to hand to rustdoc for resolution, with any generic
+    /// arguments stripped (e.g. `Result<T, E>` becomes `Result`). The
+    /// original text with generics intact is kept in `text`/`dest` so the
+    /// rendered markdown label is unaffected.
+    resolve_path: String,
+}
+
+/// A (text, dest) pair identifying a link independent of where it appears,
+/// used to resolve (and rewrite) the same link only once even if it shows
+/// up in several places, possibly across several chapters.
+type Fingerprint<'a> = (&'a str, Option<&'a str>);
+
+/// Converts links to the standard library (and any configured third-party
+/// crates) to the online documentation in a fashion similar to rustdoc
+/// intra-doc links, across every chapter in the book.
+///
+/// This collects the links from every chapter first and resolves them
+/// with a single rustdoc invocation, rather than spawning a rustdoc
+/// process per chapter, since on a large book that dominates
+/// preprocessing time. Links with the same fingerprint are only resolved
+/// once even if they recur across chapters.
+///
+/// Returns the rewritten content of each chapter, in the same order as
+/// `chapters`.
+pub fn std_links_for_book(chapters: &[&Chapter], extern_crates: &ExternCrates) -> Vec<String> {
+    for (name, krate) in extern_crates {
+        if krate.rlib_path.is_none() {
+            eprintln!(
+                "warning: extern crate `{name}` has no `rlib_path` configured, \
+                 so its links will not be resolved"
+            );
+        }
     }
 
-    // Write a Rust source file to use with rustdoc to generate intra-doc links.
+    let per_chapter: Vec<_> = chapters
+        .iter()
+        .map(|chapter| collect_markdown_links(chapter, extern_crates))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let unique_links: Vec<&MdLink<'_>> = per_chapter
+        .iter()
+        .flatten()
+        .filter(|link| seen.insert((link.text, link.dest)))
+        .collect();
+    if unique_links.is_empty() {
+        return chapters.iter().map(|chapter| chapter.content.clone()).collect();
+    }
+
+    // Run rustdoc exactly once to resolve every distinct link in the book.
     let tmp = TempDir::with_prefix("mdbook-spec-").unwrap();
-    run_rustdoc(&tmp, &links, &chapter);
+    run_rustdoc(&tmp, &unique_links, extern_crates);
 
-    // Extract the links from the generated html.
     let generated =
         fs::read_to_string(tmp.path().join("doc/a/index.html")).expect("index.html generated");
-    let urls: Vec<_> = STD_LINK_EXTRACT_RE
+    let urls: Vec<_> = LINK_EXTRACT_RE
         .captures_iter(&generated)
         .map(|cap| cap.get(1).unwrap().as_str())
         .collect();
-    if urls.len() != links.len() {
-        eprintln!(
-            "error: expected rustdoc to generate {} links, but found {} in chapter {} ({:?})",
-            links.len(),
-            urls.len(),
-            chapter.name,
-            chapter.source_path.as_ref().unwrap()
-        );
+    let urls_by_fingerprint: HashMap<Fingerprint<'_>, &str> = unique_links
+        .iter()
+        .map(|link| (link.text, link.dest))
+        .zip(&urls)
+        .map(|(fingerprint, url)| (fingerprint, *url))
+        .collect();
+
+    if urls.len() != unique_links.len() {
+        // The counts can only disagree because rustdoc failed to generate a
+        // link for at least one of them. `urls_by_fingerprint` above is
+        // necessarily missing an entry for it (zip stops at the shorter
+        // side), so go back over each chapter's own links and name whichever
+        // one isn't in the map, along with the chapter it came from, instead
+        // of a single count mismatch for the whole book.
+        for (chapter, links) in chapters.iter().zip(&per_chapter) {
+            for link in links {
+                if !urls_by_fingerprint.contains_key(&(link.text, link.dest)) {
+                    eprintln!(
+                        "error: expected rustdoc to generate a link for {} in chapter {} ({:?})",
+                        link.text,
+                        chapter.name,
+                        chapter.source_path.as_ref().unwrap()
+                    );
+                }
+            }
+        }
         process::exit(1);
     }
 
-    // Replace any disambiguated links with just the disambiguation.
-    let mut output = STD_LINK_RE
-        .replace_all(&chapter.content, |caps: &Captures| {
-            if let Some(dest) = caps.get(2) {
-                // Replace destination parenthesis with a link definition (square brackets).
-                format!("{}[{}]", &caps[1], dest.as_str())
-            } else {
-                caps[0].to_string()
-            }
-        })
-        .to_string();
+    chapters
+        .iter()
+        .zip(&per_chapter)
+        .map(|(chapter, links)| rewrite_chapter(chapter, links, &urls_by_fingerprint))
+        .collect()
+}
 
-    // Append the link definitions to the bottom of the chapter.
+/// Rewrites a single chapter's links in place, looking up each one's
+/// generated URL by its fingerprint.
+fn rewrite_chapter(
+    chapter: &Chapter,
+    links: &[MdLink<'_>],
+    urls_by_fingerprint: &HashMap<Fingerprint<'_>, &str>,
+) -> String {
+    if links.is_empty() {
+        return chapter.content.clone();
+    }
+
+    let url_for = |link: &MdLink<'_>| {
+        urls_by_fingerprint
+            .get(&(link.text, link.dest))
+            .copied()
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "error: no resolved link for {} in chapter {} ({:?})",
+                    link.text,
+                    chapter.name,
+                    chapter.source_path.as_ref().unwrap()
+                );
+                process::exit(1);
+            })
+    };
+
+    // Replace each link span in place with a reference-style link, editing
+    // only the exact bytes of the link itself rather than scanning the
+    // whole chapter again.
+    let mut output = String::with_capacity(chapter.content.len());
+    let mut last_end = 0;
+    for link in links {
+        output.push_str(&chapter.content[last_end..link.range.start]);
+        if let Some(dest) = link.dest {
+            // Replace destination parenthesis with a link definition (square brackets).
+            write!(output, "{}[{dest}]", link.text).unwrap();
+        } else {
+            output.push_str(link.text);
+        }
+        last_end = link.range.end;
+    }
+    output.push_str(&chapter.content[last_end..]);
+
+    // Append the link definitions to the bottom of the chapter, one per
+    // distinct fingerprint (the same link may occur more than once).
     write!(output, "\n").unwrap();
-    for ((link, dest), url) in links.iter().zip(urls) {
+    let mut written = HashSet::new();
+    for link in links {
+        if !written.insert((link.text, link.dest)) {
+            continue;
+        }
         // Convert links to be relative so that links work offline and
         // with the linkchecker.
-        let url = relative_url(url, chapter);
-        if let Some(dest) = dest {
+        let url = relative_url(url_for(link), chapter);
+        if let Some(dest) = link.dest {
             write!(output, "[{dest}]: {url}\n").unwrap();
         } else {
-            write!(output, "{link}: {url}\n").unwrap();
+            write!(output, "{}: {url}\n", link.text).unwrap();
         }
     }
 
     output
 }
 
-/// Collects all markdown links, excluding those that already have link definitions.
+/// Returns whether the given link kind could plausibly be an intra-doc
+/// link, as opposed to something the author clearly meant literally or
+/// already pointed somewhere explicit.
+///
+/// This mirrors rustdoc's own `may_be_doc_link`: autolinks
+/// (`<std::fmt>`) and emails are never intra-doc links, since they are
+/// written exactly as the author intends them to render. The plain
+/// (non-`*Unknown`) reference/collapsed/shortcut variants are also
+/// excluded: pulldown_cmark only reports one of those when it found a
+/// real matching `[label]: ...` definition elsewhere in the chapter, so
+/// the author already gave this link an explicit destination and it
+/// shouldn't be overridden. Besides inline links, only the `*Unknown`
+/// variants, produced by our [`collect_markdown_links`] broken-link
+/// callback for a `` [`code`] `` style link with no such definition, are
+/// candidates here.
+fn may_be_doc_link(link_type: LinkType) -> bool {
+    match link_type {
+        LinkType::Inline
+        | LinkType::ReferenceUnknown
+        | LinkType::CollapsedUnknown
+        | LinkType::ShortcutUnknown => true,
+        LinkType::Reference
+        | LinkType::Collapsed
+        | LinkType::Shortcut
+        | LinkType::Autolink
+        | LinkType::Email => false,
+    }
+}
+
+/// Strips generic arguments from a path so it can be used as an
+/// intra-doc resolution key, e.g. `Vec<T>` becomes `Vec` and
+/// `Result<T, E>` becomes `Result`. This is the same preprocessing
+/// rustdoc itself does before resolving a link.
+///
+/// Brackets are balanced while scanning, so nested generics (`Foo<Bar<T>>`)
+/// are stripped as a whole. If the brackets are unbalanced, or there are
+/// no generics at all, the original path is returned unchanged.
+fn strip_generics(path: &str) -> Cow<'_, str> {
+    if !path.contains('<') {
+        return Cow::Borrowed(path);
+    }
+    let mut result = String::with_capacity(path.len());
+    let mut depth = 0i32;
+    for c in path.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth < 0 {
+                    return Cow::Borrowed(path);
+                }
+            }
+            _ if depth == 0 => result.push(c),
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Cow::Borrowed(path);
+    }
+    Cow::Owned(result)
+}
+
+/// Returns whether `root` names one of the standard library crates.
+fn is_std_root(root: &str) -> bool {
+    matches!(root, "std" | "core" | "alloc" | "proc_macro" | "test")
+}
+
+/// Collects all markdown links that look like they could be std links (or
+/// links into a configured extern crate that has an `rlib_path`),
+/// excluding those that already have link definitions.
 ///
-/// Returns a `Vec` of `(link, Option<dest>)` where markdown text like
-/// ``[`std::fmt`]`` would return that as a link. The dest is optional, for
-/// example ``[`Option`](std::option::Option)`` would have the part in
-/// parentheses as the dest.
-fn collect_markdown_links(chapter: &Chapter) -> Vec<(&str, Option<&str>)> {
-    let mut links: Vec<_> = STD_LINK_RE
-        .captures_iter(&chapter.content)
-        .map(|cap| {
-            if let Some(no_dest) = cap.get(3) {
-                (no_dest.as_str(), None)
-            } else {
-                (
-                    cap.get(1).unwrap().as_str(),
-                    Some(cap.get(2).unwrap().as_str()),
-                )
+/// This drives a real `pulldown_cmark` parser over the chapter, which
+/// naturally ignores anything inside a code span, code block, or
+/// blockquote that isn't actual markdown syntax, and gives exact byte
+/// spans for each link so it can be edited in place later.
+fn collect_markdown_links<'a>(
+    chapter: &'a Chapter,
+    extern_crates: &ExternCrates,
+) -> Vec<MdLink<'a>> {
+    let content = chapter.content.as_str();
+    // `[`code`]`-style links are written without a link definition, so
+    // they would normally be "broken" reference links that pulldown_cmark
+    // ignores. Resolve them to their own label so the parser emits a
+    // `Tag::Link` we can inspect; we validate the shape ourselves below.
+    let mut resolve_broken = |link: BrokenLink| -> Option<(CowStr, CowStr)> {
+        Some((link.reference.to_string().into(), String::new().into()))
+    };
+    let parser = Parser::new_with_broken_link_callback(
+        content,
+        Options::empty(),
+        Some(&mut resolve_broken),
+    );
+
+    let mut links: Vec<_> = parser
+        .into_offset_iter()
+        .filter_map(|(event, range)| {
+            let Event::Start(Tag::Link { link_type, .. }) = event else {
+                return None;
+            };
+            if !may_be_doc_link(link_type) {
+                return None;
+            }
+            let full = &content[range.clone()];
+            let (text, dest) = match full.rfind("](") {
+                Some(paren) if full.ends_with(')') => {
+                    (&full[..paren + 1], Some(&full[paren + 2..full.len() - 1]))
+                }
+                // A `*Unknown` reference/collapsed link's range covers its
+                // whole `[`code`][label]` span, but an unresolved trailing
+                // `[label]` isn't part of what we rewrite or key on, so
+                // narrow `text` down to just the leading `` [`code`] ``
+                // portion.
+                _ => (CODE_LABEL_RE.captures(full)?.get(0).unwrap().as_str(), None),
+            };
+            let path = match dest {
+                Some(dest) => dest,
+                None => {
+                    let (_, [path]) = CODE_LABEL_RE.captures(full)?.extract();
+                    path
+                }
+            };
+            let root = LINK_PATH_RE.captures(path)?.name("root").unwrap().as_str();
+            let has_rlib = extern_crates
+                .get(root)
+                .is_some_and(|krate| krate.rlib_path.is_some());
+            if !is_std_root(root) && !has_rlib {
+                return None;
             }
+            let resolve_path = strip_generics(path).into_owned();
+            Some(MdLink { range, text, dest, resolve_path })
         })
         .collect();
     if links.is_empty() {
-        return vec![];
+        return links;
     }
-    links.sort();
-    links.dedup();
+
     // Remove any links that already have a link definition. We don't want
     // to override what the author explicitly specified.
     let existing_labels: HashSet<_> = LINK_DEF_RE
-        .captures_iter(&chapter.content)
+        .captures_iter(content)
         .map(|cap| cap.get(1).unwrap().as_str())
         .collect();
-    links.retain(|(link, dest)| {
-        let mut tmp = None;
-        let label: &str = dest.map_or(link, |d| {
-            tmp = Some(format!("[`{d}`]"));
-            tmp.as_deref().unwrap()
-        });
+    links.retain(|link| {
+        let owned;
+        let label: &str = match link.dest {
+            Some(dest) => {
+                owned = format!("[`{dest}`]");
+                &owned
+            }
+            None => link.text,
+        };
         !existing_labels.contains(label)
     });
 
@@ -150,7 +406,7 @@ fn collect_markdown_links(chapter: &Chapter) -> Vec<(&str, Option<&str>)> {
 /// generate intra-doc links on them.
 ///
 /// The output will be in the given `tmp` directory.
-fn run_rustdoc(tmp: &TempDir, links: &[(&str, Option<&str>)], chapter: &Chapter) {
+fn run_rustdoc(tmp: &TempDir, links: &[&MdLink<'_>], extern_crates: &ExternCrates) {
     let src_path = tmp.path().join("a.rs");
     // Allow redundant since there could some in-scope things that are
     // technically not necessary, but we don't care about (like
@@ -159,34 +415,45 @@ fn run_rustdoc(tmp: &TempDir, links: &[(&str, Option<&str>)], chapter: &Chapter)
         "#![deny(rustdoc::broken_intra_doc_links)]\n\
          #![allow(rustdoc::redundant_explicit_links)]\n"
     );
-    for (link, dest) in links {
-        write!(src, "//! - {link}").unwrap();
-        if let Some(dest) = dest {
-            write!(src, "({})", dest).unwrap();
-        }
-        src.push('\n');
+    for link in links {
+        // Always write an explicit destination, using the generics-stripped
+        // resolution path, regardless of whether the author wrote one: a
+        // bare `[`std::result::Result<T, E>`]` needs `std::result::Result`
+        // to resolve correctly.
+        writeln!(src, "//! - {}({})", link.text, link.resolve_path).unwrap();
     }
     writeln!(
         src,
         "extern crate alloc;\n\
          extern crate proc_macro;\n\
-         extern crate test;\n"
+         extern crate test;"
     )
     .unwrap();
+    // Only declare crates we can actually satisfy with `--extern`: an
+    // `extern crate` with no matching rlib is a hard `E0463` error from
+    // rustc itself, raised before doc links are even checked, which would
+    // take down the whole invocation rather than just failing resolution
+    // for that crate's links.
+    for (name, krate) in extern_crates {
+        if krate.rlib_path.is_some() {
+            writeln!(src, "extern crate {name};").unwrap();
+        }
+    }
     fs::write(&src_path, &src).unwrap();
-    let output = Command::new("rustdoc")
-        .arg("--edition=2021")
-        .arg(&src_path)
-        .current_dir(tmp.path())
-        .output()
-        .expect("rustdoc installed");
+    let mut cmd = Command::new("rustdoc");
+    cmd.arg("--edition=2021").arg(&src_path).current_dir(tmp.path());
+    for (name, krate) in extern_crates {
+        // Tell rustdoc where items from this crate are hosted so the
+        // generated links point at docs.rs instead of a local path.
+        cmd.arg("--extern-html-root-url")
+            .arg(format!("{name}=https://docs.rs/{name}/{}/", krate.version));
+        if let Some(rlib_path) = &krate.rlib_path {
+            cmd.arg("--extern").arg(format!("{name}={}", rlib_path.display()));
+        }
+    }
+    let output = cmd.output().expect("rustdoc installed");
     if !output.status.success() {
-        eprintln!(
-            "error: failed to extract std links ({:?}) in chapter {} ({:?})\n",
-            output.status,
-            chapter.name,
-            chapter.source_path.as_ref().unwrap()
-        );
+        eprintln!("error: failed to extract links for the book ({:?})\n", output.status);
         io::stderr().write_all(&output.stderr).unwrap();
         process::exit(1);
     }
@@ -197,7 +464,13 @@ static DOC_URL: Lazy<Regex> = Lazy::new(|| {
 });
 
 /// Converts a URL to doc.rust-lang.org to be relative.
+///
+/// docs.rs URLs (for third-party crates) are left absolute, since they
+/// aren't served alongside the book and can't be made relative to it.
 fn relative_url(url: &str, chapter: &Chapter) -> String {
+    if url.starts_with("https://docs.rs/") {
+        return url.to_string();
+    }
     // Set SPEC_RELATIVE=0 to disable this, which can be useful for working locally.
     if std::env::var("SPEC_RELATIVE").as_deref() != Ok("0") {
         let Some(url_start) = DOC_URL.shortest_match(url) else {
@@ -212,3 +485,212 @@ fn relative_url(url: &str, chapter: &Chapter) -> String {
         url.to_string()
     }
 }
+
+/// How the bare-URL lint in [`check_bare_urls`] should behave, configured
+/// via `[preprocessor.spec] bare-urls` in `book.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BareUrlMode {
+    /// Don't check for bare URLs.
+    #[default]
+    Off,
+    /// Print a warning naming the chapter and the URL, but leave the
+    /// chapter unchanged.
+    Warn,
+    /// Rewrite each bare URL into an angle-bracket autolink so it renders
+    /// as a real link.
+    Fix,
+}
+
+/// A bare `http(s)://` URL, similar to rustdoc's `bare_urls` lint.
+static BARE_URL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"https?://[^\s<>()\[\]]+").unwrap());
+
+/// Trims trailing sentence punctuation off a [`BARE_URL_RE`] match, the
+/// way CommonMark's extended-autolink matching does, so a URL at the end
+/// of a sentence like `See https://example.com/foo.` doesn't have the
+/// `.` baked into the link target.
+///
+/// `(` and `)` are already excluded from [`BARE_URL_RE`] itself, so an
+/// unbalanced trailing `)` can't occur here; a trailing `"` is trimmed
+/// only if it isn't matched by an opening `"` earlier in the URL.
+fn trim_trailing_punctuation(url: &str) -> &str {
+    let mut end = url.len();
+    loop {
+        let trimmed = &url[..end];
+        let Some(c) = trimmed.chars().next_back() else {
+            break;
+        };
+        match c {
+            '.' | ',' | ';' | ':' | '!' | '?' => end -= c.len_utf8(),
+            '"' if trimmed.matches('"').count() % 2 == 1 => end -= c.len_utf8(),
+            _ => break,
+        }
+    }
+    &url[..end]
+}
+
+/// Scans a chapter's prose for unlinked `http(s)://` URLs and, depending
+/// on `mode`, warns about them or rewrites them into real links.
+///
+/// This walks the same kind of `pulldown_cmark` event stream as
+/// [`collect_markdown_links`], so a URL inside a code span, code block,
+/// or an existing link (including an existing [`LINK_DEF_RE`] definition,
+/// which isn't emitted as visible text at all) is left untouched.
+pub fn check_bare_urls(chapter: &Chapter, mode: BareUrlMode) -> String {
+    let content = chapter.content.as_str();
+    if mode == BareUrlMode::Off {
+        return content.to_string();
+    }
+
+    let mut link_depth = 0;
+    let mut in_code_block = false;
+    let mut bare_urls = Vec::new();
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { .. }) => link_depth += 1,
+            Event::End(TagEnd::Link) => link_depth -= 1,
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(_) if link_depth == 0 && !in_code_block => {
+                for m in BARE_URL_RE.find_iter(&content[range.clone()]) {
+                    let trimmed_len = trim_trailing_punctuation(m.as_str()).len();
+                    bare_urls.push(range.start + m.start()..range.start + m.start() + trimmed_len);
+                }
+            }
+            _ => {}
+        }
+    }
+    if bare_urls.is_empty() {
+        return content.to_string();
+    }
+
+    match mode {
+        BareUrlMode::Off => unreachable!(),
+        BareUrlMode::Warn => {
+            for url in &bare_urls {
+                eprintln!(
+                    "warning: bare URL `{}` in chapter {} ({:?})",
+                    &content[url.clone()],
+                    chapter.name,
+                    chapter.source_path.as_ref().unwrap()
+                );
+            }
+            content.to_string()
+        }
+        BareUrlMode::Fix => {
+            let mut output = String::with_capacity(content.len() + bare_urls.len() * 2);
+            let mut last_end = 0;
+            for url in &bare_urls {
+                output.push_str(&content[last_end..url.start]);
+                write!(output, "<{}>", &content[url.clone()]).unwrap();
+                last_end = url.end;
+            }
+            output.push_str(&content[last_end..]);
+            output
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(content: &str) -> Chapter {
+        Chapter::new("test", content.to_string(), "test.md", Vec::new())
+    }
+
+    #[test]
+    fn link_path_re_accepts_generics_directly_after_root() {
+        let root = |path| LINK_PATH_RE.captures(path).unwrap().name("root").unwrap().as_str();
+        assert_eq!(root("Vec<T>"), "Vec");
+        assert_eq!(root("Result<T, E>"), "Result");
+        assert_eq!(root("std::result::Result<T, E>"), "std");
+        assert_eq!(root("std::vec::Vec"), "std");
+    }
+
+    #[test]
+    fn strip_generics_removes_angle_bracket_args() {
+        assert_eq!(strip_generics("Vec<T>"), "Vec");
+        assert_eq!(strip_generics("Result<T, E>"), "Result");
+        assert_eq!(strip_generics("std::result::Result<T, E>"), "std::result::Result");
+        assert_eq!(strip_generics("Foo<Bar<T>>"), "Foo");
+        assert_eq!(strip_generics("Option"), "Option");
+        // Unbalanced brackets fall back to the original path unchanged.
+        assert_eq!(strip_generics("Foo<Bar"), "Foo<Bar");
+        assert_eq!(strip_generics("Foo>Bar"), "Foo>Bar");
+    }
+
+    #[test]
+    fn collect_markdown_links_handles_generics() {
+        // `collect_markdown_links` only keeps paths whose root passes
+        // `is_std_root` (or is a configured extern crate), so these are
+        // written with an explicit `std::` root rather than the bare
+        // `Vec<T>`/`Result<T, E>`, whose roots `Vec`/`Result` are dropped.
+        let chapter =
+            chapter("See [`std::vec::Vec<T>`] and [`std::result::Result<T, E>`] here.");
+        let links = collect_markdown_links(&chapter, &ExternCrates::default());
+        let paths: Vec<_> = links.iter().map(|link| link.resolve_path.as_str()).collect();
+        assert_eq!(paths, ["std::vec::Vec", "std::result::Result"]);
+    }
+
+    #[test]
+    fn trim_trailing_punctuation_strips_sentence_punctuation() {
+        assert_eq!(trim_trailing_punctuation("https://example.com/foo."), "https://example.com/foo");
+        assert_eq!(trim_trailing_punctuation("https://example.com/x,"), "https://example.com/x");
+        assert_eq!(trim_trailing_punctuation("https://example.com/x"), "https://example.com/x");
+        // An unmatched trailing quote is trimmed...
+        assert_eq!(trim_trailing_punctuation("https://example.com/x\""), "https://example.com/x");
+        // ...but a balanced one is part of the URL and is left alone.
+        assert_eq!(
+            trim_trailing_punctuation("https://example.com/\"x\""),
+            "https://example.com/\"x\""
+        );
+    }
+
+    #[test]
+    fn may_be_doc_link_excludes_autolinks_emails_and_resolved_references() {
+        assert!(may_be_doc_link(LinkType::Inline));
+        assert!(may_be_doc_link(LinkType::ReferenceUnknown));
+        assert!(may_be_doc_link(LinkType::CollapsedUnknown));
+        assert!(may_be_doc_link(LinkType::ShortcutUnknown));
+        assert!(!may_be_doc_link(LinkType::Autolink));
+        assert!(!may_be_doc_link(LinkType::Email));
+        // These resolve through a real `[label]: ...` definition elsewhere
+        // in the chapter, so the author already gave them a destination.
+        assert!(!may_be_doc_link(LinkType::Reference));
+        assert!(!may_be_doc_link(LinkType::Collapsed));
+        assert!(!may_be_doc_link(LinkType::Shortcut));
+    }
+
+    #[test]
+    fn collect_markdown_links_skips_reference_links_with_a_real_definition() {
+        let chapter = chapter(
+            "See [`std::collections::HashMap`][hashmap-ref].\n\n\
+             [hashmap-ref]: ../collections.md#hashmap\n",
+        );
+        let links = collect_markdown_links(&chapter, &ExternCrates::default());
+        assert!(links.is_empty(), "expected no candidates, got {links:?}");
+    }
+
+    #[test]
+    fn collect_markdown_links_trims_an_unresolved_trailing_label() {
+        let chapter = chapter("See [`std::vec::Vec`][orphan-ref] here.");
+        let links = collect_markdown_links(&chapter, &ExternCrates::default());
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text, "[`std::vec::Vec`]");
+        assert_eq!(links[0].dest, None);
+    }
+
+    #[test]
+    fn collect_markdown_links_requires_rlib_for_extern_crates() {
+        let mut extern_crates = ExternCrates::default();
+        extern_crates.insert(
+            "libc".to_string(),
+            ExternCrate { version: "0.2.150".to_string(), rlib_path: None },
+        );
+        let chapter = chapter("See [`libc::c_int`] here.");
+        assert!(collect_markdown_links(&chapter, &extern_crates).is_empty());
+
+        extern_crates.get_mut("libc").unwrap().rlib_path = Some(PathBuf::from("libc.rlib"));
+        assert_eq!(collect_markdown_links(&chapter, &extern_crates).len(), 1);
+    }
+}